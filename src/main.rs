@@ -1,53 +1,417 @@
 /*
- * Rust parser combinator 
+ * Rust parser combinator
  * From here (https://bodil.lol/parser-combinators/)
  */
 #![type_length_limit="1137931"]
 
+use std::fmt;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
-struct Element 
+struct Element
 {
     name: String,
     attributes: Vec<(String, String)>,
     children: Vec<Element>
 }
 
-// Make a trait for the parse result 
-type ParseResult<'a, Output> = Result<(&'a str, Output), &'a str>;
+// Type alias for the parse result. `I` is the input stream type,
+// `E` is the error type: the bare unconsumed input `I` for the
+// generic combinators, or a structured `ParseError` (see below) for
+// the labelled XML grammar parsers.
+type ParseResult<I, O, E> = Result<(I, O), E>;
+
+// A symbolic description of the grammar a parser matches, e.g. for
+// auto-generated documentation or for inspecting how a combined parser
+// is wired. Combinators with a well-known shape (a literal, a
+// repetition, a choice, a label) build one of these directly;
+// combinators that transform a result in a way that can't be recovered
+// generically (`and_then`, bare predicates) fall back to `Opaque`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Grammar {
+    Literal(String),
+    Opaque,
+    Seq(Vec<Grammar>),
+    Choice(Vec<Grammar>),
+    Repeat { min: usize, inner: Box<Grammar> },
+    Named(String, Box<Grammar>),
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Grammar::Literal(s) => write!(f, "{}", s),
+            Grammar::Opaque => write!(f, "..."),
+            Grammar::Seq(parts) =>
+            {
+                let rendered: Vec<String> = parts.iter().map(|g| g.to_string()).collect();
+                write!(f, "{}", rendered.join(", "))
+            }
+            Grammar::Choice(parts) =>
+            {
+                let rendered: Vec<String> = parts.iter().map(|g| g.to_string()).collect();
+                write!(f, "{}", rendered.join(" | "))
+            }
+            Grammar::Repeat { min: 0, inner } => write!(f, "{{ {} }}", inner),
+            Grammar::Repeat { min: _, inner } => write!(f, "{}, {{ {} }}", inner, inner),
+            Grammar::Named(name, inner) => write!(f, "{} = {}", name, inner),
+        }
+    }
+}
+
+trait Parser<I, O, E> {
+    fn parse(&self, input: I) -> ParseResult<I, O, E>;
+
+    // A symbolic description of what this parser matches. Most
+    // combinators know their own shape and override this; the default
+    // covers the rest (bare closures built from `and_then`, raw
+    // predicates, ...).
+    fn grammar(&self) -> Grammar
+    {
+        Grammar::Opaque
+    }
+
+    // Chain a map onto this parser, type-erasing the result so that
+    // deeply nested combinator chains don't blow out the compiler's
+    // type_length_limit.
+    fn map<'a, F, B>(self, map_fn: F) -> BoxedParser<'a, I, B, E>
+    where
+        Self: Sized + 'a,
+        I: 'a,
+        O: 'a,
+        E: 'a,
+        B: 'a,
+        F: Fn(O) -> B + 'a,
+    {
+        BoxedParser::new(map(self, map_fn))
+    }
+
+    // Chain a predicate onto this parser, boxed for the same reason as map().
+    fn pred<'a, F>(self, predicate: F) -> BoxedParser<'a, I, O, I>
+    where
+        Self: Sized + Parser<I, O, I> + 'a,
+        I: Copy + 'a,
+        O: 'a,
+        F: Fn(&O) -> bool + 'a,
+    {
+        BoxedParser::new(pred(self, predicate))
+    }
 
-trait Parser<'a, Output> {
-    fn parse(&self, input: &'a str) ->  ParseResult<'a, Output>;
+    // Run this parser, but require that it consume the whole input.
+    // Combinators happily succeed while leaving a non-empty tail (e.g.
+    // `element_start().parse("<a> garbage")` is `Ok` with "garbage"
+    // silently dropped); this turns that leftover tail into an error
+    // instead of a partial, misleading success.
+    fn parse_complete<'a>(&self, input: I) -> ParseResult<I, O, E>
+    where
+        Self: Sized,
+        I: ParserInput<'a> + 'a,
+        E: IncompleteParse<I>,
+    {
+        match self.parse(input)
+        {
+            Ok((rest, out)) if rest.input_len() == 0 => Ok((rest, out)),
+            Ok((rest, _)) => Err(E::from_remaining(rest)),
+            Err(err) => Err(err),
+        }
+    }
 }
 
 // Implement this trait for any function that matches the
 // signature of a parser
-impl<'a, F, Output> Parser<'a, Output> for F
+impl<I, O, E, F> Parser<I, O, E> for F
 where
-    F: Fn(&'a str) -> ParseResult<Output>,
+    F: Fn(I) -> ParseResult<I, O, E>,
 {
-    fn parse(&self, input: &'a str) -> ParseResult<'a, Output>
+    fn parse(&self, input: I) -> ParseResult<I, O, E>
     {
         return self(input);
     }
 }
 
+// A type-erased parser. Wrapping intermediate stages in a BoxedParser
+// stops method-chained combinators (map/pred/...) from building up a
+// single monstrous nested generic type.
+struct BoxedParser<'a, I, O, E> {
+    parser: Box<dyn Parser<I, O, E> + 'a>,
+}
+
+impl<'a, I, O, E> BoxedParser<'a, I, O, E> {
+    fn new<P>(parser: P) -> Self
+    where
+        P: Parser<I, O, E> + 'a,
+    {
+        BoxedParser {
+            parser: Box::new(parser),
+        }
+    }
+}
+
+impl<'a, I, O, E> Parser<I, O, E> for BoxedParser<'a, I, O, E> {
+    fn parse(&self, input: I) -> ParseResult<I, O, E>
+    {
+        return self.parser.parse(input);
+    }
+
+    fn grammar(&self) -> Grammar
+    {
+        return self.parser.grammar();
+    }
+}
+
+// Convenience alias for the common case the XML grammar below is built
+// on: input is `&'a str` and errors are the structured `ParseError<'a>`
+// below (rather than a bare unconsumed tail). This keeps the
+// grammar-level functions reading the way they did before Parser grew
+// input/error type parameters.
+trait StrParser<'a, Output>: Parser<&'a str, Output, ParseError<'a>> {}
+
+impl<'a, Output, P> StrParser<'a, Output> for P
+where
+    P: Parser<&'a str, Output, ParseError<'a>>,
+{}
+
+// A structured parse failure: the unconsumed input at the point of
+// failure (so a caller can compute how far the parse got), and the set
+// of things that would have been accepted there instead of what was
+// found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ParseError<'a> {
+    input: &'a str,
+    expected: Vec<&'static str>,
+}
+
+impl<'a> ParseError<'a> {
+    fn new(input: &'a str, expected: &'static str) -> Self
+    {
+        ParseError { input, expected: vec![expected] }
+    }
+
+    // Byte offset of this failure relative to `original`. `input` must
+    // be a suffix of `original` -- true of every error produced by the
+    // combinators below, since they only ever strip a prefix off the
+    // front of the input they're given.
+    fn offset(&self, original: &str) -> usize
+    {
+        original.len() - self.input.len()
+    }
+}
+
+// Lets `either` combine the errors of two failed alternatives into one.
+// For the bare `&str` error (no position/label info to combine) this
+// just keeps the second attempt's error.
+trait MergeError: Sized {
+    fn merge(self, other: Self) -> Self;
+}
+
+impl<'a> MergeError for &'a str {
+    fn merge(self, other: Self) -> Self
+    {
+        other
+    }
+}
+
+impl<'a> MergeError for ParseError<'a> {
+    fn merge(mut self, mut other: Self) -> Self
+    {
+        // Whichever alternative consumed more input got further into
+        // the grammar and is the more useful error to surface. Only
+        // when both failed at the same point does it make sense to
+        // union their expected-sets.
+        if self.input.len() != other.input.len()
+        {
+            return if self.input.len() < other.input.len() { self } else { other };
+        }
+
+        self.expected.append(&mut other.expected);
+        self
+    }
+}
+
+// Lets `parse_complete` turn leftover, unconsumed input into an error
+// value, same trick as `MergeError` for turning a bare `&str` tail or a
+// labelled `ParseError` into the error type a given parser actually uses.
+trait IncompleteParse<I>: Sized {
+    fn from_remaining(remaining: I) -> Self;
+}
+
+impl<'a> IncompleteParse<&'a str> for &'a str {
+    fn from_remaining(remaining: &'a str) -> Self
+    {
+        remaining
+    }
+}
+
+impl<'a> IncompleteParse<&'a str> for ParseError<'a> {
+    fn from_remaining(remaining: &'a str) -> Self
+    {
+        ParseError::new(remaining, "end of input")
+    }
+}
+
+// Attach a human-readable label to a sub-parser, converting its bare
+// `&str` error (the unconsumed tail) into a `ParseError` that says what
+// was expected there.
+struct Labelled<P> {
+    label: &'static str,
+    parser: P,
+}
+
+impl<'a, P, A> Parser<&'a str, A, ParseError<'a>> for Labelled<P>
+where
+    P: Parser<&'a str, A, &'a str>,
+{
+    fn parse(&self, input: &'a str) -> ParseResult<&'a str, A, ParseError<'a>>
+    {
+        match self.parser.parse(input)
+        {
+            Ok(ok) => Ok(ok),
+            Err(tail) => Err(ParseError::new(tail, self.label)),
+        }
+    }
+
+    fn grammar(&self) -> Grammar
+    {
+        Grammar::Named(self.label.to_string(), Box::new(self.parser.grammar()))
+    }
+}
+
+fn context<'a, P, A>(label: &'static str, parser: P) -> Labelled<P>
+where
+    P: Parser<&'a str, A, &'a str>,
+{
+    Labelled { label, parser }
+}
+
+/*
+ * ParserInput
+ * Abstracts over the handful of operations the combinators below need
+ * from an input stream, so the same combinator set can run over a
+ * `&str`, a `&[u8]`, or (given an impl) a pre-lexed `&[Token]` stream.
+ */
+trait ParserInput<'a>: Copy
+{
+    type Item: Copy + PartialEq;
+
+    // Strip `prefix` off the front of the input, if the input starts with it.
+    fn advance(self, prefix: Self) -> Option<Self>;
+
+    // Take the next item off the front of the input, along with what's left.
+    fn peek(self) -> Option<(Self::Item, Self)>;
+
+    // Split the input into its first `at` elements and the remainder.
+    fn split_at_index(self, at: usize) -> (Self, Self);
+
+    // Number of elements remaining in the input.
+    fn input_len(self) -> usize;
+}
+
+impl<'a> ParserInput<'a> for &'a str
+{
+    type Item = char;
+
+    fn advance(self, prefix: Self) -> Option<Self>
+    {
+        if self.starts_with(prefix)
+        {
+            Some(&self[prefix.len()..])
+        }
+        else
+        {
+            None
+        }
+    }
+
+    fn peek(self) -> Option<(char, Self)>
+    {
+        let next = self.chars().next()?;
+        let (_, rest) = self.split_at_index(next.len_utf8());
+        Some((next, rest))
+    }
+
+    fn split_at_index(self, at: usize) -> (Self, Self)
+    {
+        (&self[..at], &self[at..])
+    }
+
+    fn input_len(self) -> usize
+    {
+        self.len()
+    }
+}
+
+impl<'a> ParserInput<'a> for &'a [u8]
+{
+    type Item = u8;
+
+    fn advance(self, prefix: Self) -> Option<Self>
+    {
+        if self.starts_with(prefix)
+        {
+            Some(&self[prefix.len()..])
+        }
+        else
+        {
+            None
+        }
+    }
+
+    fn peek(self) -> Option<(u8, Self)>
+    {
+        let next = *self.first()?;
+        let (_, rest) = self.split_at_index(1);
+        Some((next, rest))
+    }
+
+    fn split_at_index(self, at: usize) -> (Self, Self)
+    {
+        (&self[..at], &self[at..])
+    }
+
+    fn input_len(self) -> usize
+    {
+        self.len()
+    }
+}
 
 /*
  * match a literal
  */
-fn match_literal<'a>(expected: &'static str) -> impl Parser<'a, ()>
+struct Literal<I> {
+    expected: I,
+}
+
+impl<'a, I> Parser<I, (), I> for Literal<I>
+where
+    I: ParserInput<'a> + 'a + fmt::Debug,
 {
-    move |input: &'a str| match input.get(0..expected.len()) 
+    fn parse(&self, input: I) -> ParseResult<I, (), I>
     {
-        Some(next) if next == expected => Ok((&input[expected.len()..], ())),
-        _ => Err(input),
+        match input.advance(self.expected)
+        {
+            Some(rest) => Ok((rest, ())),
+            None => Err(input),
+        }
+    }
+
+    fn grammar(&self) -> Grammar
+    {
+        Grammar::Literal(format!("{:?}", self.expected))
     }
 }
 
+fn match_literal<'a, I>(expected: I) -> Literal<I>
+where
+    I: ParserInput<'a> + 'a,
+{
+    Literal { expected }
+}
+
 /*
  * match an identifier
  */
-fn identifier(input: &str) -> ParseResult<String>
+fn identifier<'a>(input: &'a str) -> ParseResult<&'a str, String, &'a str>
 {
     let mut matched = String::new();
     let mut chars = input.chars();
@@ -58,7 +422,7 @@ fn identifier(input: &str) -> ParseResult<String>
         _ => return Err(input),
     }
 
-    while let Some(next) = chars.next() 
+    while let Some(next) = chars.next()
     {
         if next.is_alphanumeric() || next == '-' {
             matched.push(next);
@@ -75,41 +439,82 @@ fn identifier(input: &str) -> ParseResult<String>
 
 // combinator parser for a pair
 // this takes two parsers and combines them into a single parser
-fn pair<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, (R1, R2)>
+struct PairOf<P1, P2> {
+    parser1: P1,
+    parser2: P2,
+}
+
+impl<I, E, P1, P2, R1, R2> Parser<I, (R1, R2), E> for PairOf<P1, P2>
 where
-    P1: Parser<'a, R1>,
-    P2: Parser<'a, R2>,
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
 {
-    move |input| 
+    fn parse(&self, input: I) -> ParseResult<I, (R1, R2), E>
     {
-        parser1.parse(input).and_then(|(next_input, result1)|
+        self.parser1.parse(input).and_then(|(next_input, result1)|
         {
-            parser2.parse(next_input).map(|(last_input, result2)| (last_input, (result1, result2)))
+            self.parser2.parse(next_input).map(|(last_input, result2)| (last_input, (result1, result2)))
         })
     }
+
+    fn grammar(&self) -> Grammar
+    {
+        Grammar::Seq(vec![self.parser1.grammar(), self.parser2.grammar()])
+    }
 }
 
-// Map combinator 
+fn pair<I, E, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> PairOf<P1, P2>
+where
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
+{
+    PairOf { parser1, parser2 }
+}
+
+// Map combinator
 // We use this to change the type of the result
 // This is kind of like the rust equivalent of a functor
-fn map<'a, P, F, A, B>(parser: P, map_fn: F) -> impl Parser<'a, B>
+struct MapOf<P, F, A> {
+    parser: P,
+    map_fn: F,
+    _input: std::marker::PhantomData<A>,
+}
+
+impl<I, E, P, F, A, B> Parser<I, B, E> for MapOf<P, F, A>
+where
+    P: Parser<I, A, E>,
+    F: Fn(A) -> B,
+{
+    fn parse(&self, input: I) -> ParseResult<I, B, E>
+    {
+        self.parser.parse(input)
+            .map(|(next_input, result)| (next_input, (self.map_fn)(result)))
+    }
+
+    // map() doesn't change what strings match, only how the result is
+    // interpreted, so it matches the same grammar as its inner parser.
+    fn grammar(&self) -> Grammar
+    {
+        self.parser.grammar()
+    }
+}
+
+fn map<I, E, P, F, A, B>(parser: P, map_fn: F) -> MapOf<P, F, A>
 where
-    P: Parser<'a, A>,
+    P: Parser<I, A, E>,
     F: Fn(A) -> B,
 {
-    move |input| 
-        parser.parse(input)
-        .map(|(next_input, result)| (next_input, map_fn(result)))
+    MapOf { parser, map_fn, _input: std::marker::PhantomData }
 }
 
 /*
  * left combinator
  * Keep only the left side of a combinator pair
  */
-fn left<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, R1>
+fn left<I, E, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<I, R1, E>
 where
-    P1: Parser<'a, R1>,
-    P2: Parser<'a, R2>,
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
 {
     return map(pair(parser1, parser2), |(left, _right)| left);
 }
@@ -118,34 +523,39 @@ where
  * right combinator
  * Keep only the right side of a combinator pair
  */
-fn right<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, R2>
+fn right<I, E, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<I, R2, E>
 where
-    P1: Parser<'a, R1>,
-    P2: Parser<'a, R2>,
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
 {
     return map(pair(parser1, parser2), |(_left, right)| right);
 }
 
 // One-or-more  (.) combinator
-fn one_or_more<'a, P, A>(parser: P) -> impl Parser<'a, Vec<A>>
+struct OneOrMore<P> {
+    parser: P,
+}
+
+impl<I, E, P, A> Parser<I, Vec<A>, E> for OneOrMore<P>
 where
-    P: Parser<'a, A>,
+    I: Copy,
+    P: Parser<I, A, E>,
 {
-    move |mut input| 
+    fn parse(&self, mut input: I) -> ParseResult<I, Vec<A>, E>
     {
         let mut result = Vec::new();
 
-        if let Ok((next_input, first_item)) = parser.parse(input)
+        match self.parser.parse(input)
         {
-            input = next_input;
-            result.push(first_item);
-        }
-        else
-        {
-            return Err(input);
+            Ok((next_input, first_item)) =>
+            {
+                input = next_input;
+                result.push(first_item);
+            }
+            Err(err) => return Err(err),
         }
 
-        while let Ok((next_input, next_item)) = parser.parse(input)
+        while let Ok((next_input, next_item)) = self.parser.parse(input)
         {
             input = next_input;
             result.push(next_item);
@@ -153,18 +563,36 @@ where
 
         return Ok((input, result));
     }
+
+    fn grammar(&self) -> Grammar
+    {
+        Grammar::Repeat { min: 1, inner: Box::new(self.parser.grammar()) }
+    }
 }
 
-// Zero-or-more (*) combinator 
-fn zero_or_more<'a, P, A>(parser: P) -> impl Parser<'a, Vec<A>>
+fn one_or_more<I, E, P, A>(parser: P) -> OneOrMore<P>
 where
-    P: Parser<'a, A>
+    I: Copy,
+    P: Parser<I, A, E>,
 {
-    move |mut input|
+    OneOrMore { parser }
+}
+
+// Zero-or-more (*) combinator
+struct ZeroOrMore<P> {
+    parser: P,
+}
+
+impl<I, E, P, A> Parser<I, Vec<A>, E> for ZeroOrMore<P>
+where
+    I: Copy,
+    P: Parser<I, A, E>,
+{
+    fn parse(&self, mut input: I) -> ParseResult<I, Vec<A>, E>
     {
         let mut result = Vec::new();
 
-        while let Ok((next_input, next_item)) = parser.parse(input)
+        while let Ok((next_input, next_item)) = self.parser.parse(input)
         {
             input = next_input;
             result.push(next_item);
@@ -172,57 +600,77 @@ where
 
         return Ok((input, result));
     }
+
+    fn grammar(&self) -> Grammar
+    {
+        Grammar::Repeat { min: 0, inner: Box::new(self.parser.grammar()) }
+    }
+}
+
+fn zero_or_more<I, E, P, A>(parser: P) -> ZeroOrMore<P>
+where
+    I: Copy,
+    P: Parser<I, A, E>,
+{
+    ZeroOrMore { parser }
 }
 
 /*
- * parse any character
+ * parse any item off the front of the input
  */
-fn any_char(input: &str) -> ParseResult<char>
+fn any_item<'a, I>(input: I) -> ParseResult<I, I::Item, I>
+where
+    I: ParserInput<'a> + 'a,
 {
-    match input.chars().next() 
+    match input.peek()
     {
-        Some(next) => Ok((&input[next.len_utf8()..], next)),
-        _ => Err(input),
+        Some((item, rest)) => Ok((rest, item)),
+        None => Err(input),
     }
 }
 
+/*
+ * parse any character
+ */
+fn any_char<'a>(input: &'a str) -> ParseResult<&'a str, char, &'a str>
+{
+    any_item(input)
+}
+
 /*
  * parse and call a predicate function
  */
-fn pred<'a, P, A, F>(parser: P, predicate: F) -> impl Parser<'a, A>
+fn pred<I, P, A, F>(parser: P, predicate: F) -> impl Parser<I, A, I>
 where
-    P: Parser<'a, A>,
+    I: Copy,
+    P: Parser<I, A, I>,
     F: Fn(&A) -> bool,
 {
-    move |input| {
-        if let Ok((next_input, value)) = parser.parse(input) 
-        {
-            if predicate(&value)
-            {
-                return Ok((next_input, value));
-            }
-        }
-        return Err(input);
+    move |input| match parser.parse(input)
+    {
+        Ok((next_input, value)) if predicate(&value) => Ok((next_input, value)),
+        Ok(_) => Err(input),
+        Err(err) => Err(err),
     }
 }
 
 /*
  * parse any whitespace
  */
-fn whitespace_char<'a>() -> impl Parser<'a, char>
+fn whitespace_char<'a>() -> impl StrParser<'a, char>
 {
-    return pred(any_char, |c| c.is_whitespace());
+    return context("whitespace", pred(any_char, |c| c.is_whitespace()));
 }
 
 /*
- * parse zero or more/one or more whitespace 
+ * parse zero or more/one or more whitespace
  */
-fn one_or_more_space<'a>() -> impl Parser<'a, Vec<char>>
+fn one_or_more_space<'a>() -> impl StrParser<'a, Vec<char>>
 {
     return one_or_more(whitespace_char());
 }
 
-fn zero_or_more_space<'a>() -> impl Parser<'a, Vec<char>>
+fn zero_or_more_space<'a>() -> impl StrParser<'a, Vec<char>>
 {
     return zero_or_more(whitespace_char());
 }
@@ -230,9 +678,9 @@ fn zero_or_more_space<'a>() -> impl Parser<'a, Vec<char>>
 /*
  * parse a quoted string
  */
-fn quoted_string<'a>() -> impl Parser<'a, String>
+fn quoted_string<'a>() -> impl StrParser<'a, String>
 {
-    map(
+    context("a quoted string", map(
         right(
             match_literal("\""),
             left(
@@ -241,50 +689,182 @@ fn quoted_string<'a>() -> impl Parser<'a, String>
             ),
         ),
         |chars| chars.into_iter().collect(),
-    )
+    ))
 }
 
 // ======== ATTRIBUTES ======== //
 
-// This is now quite easy since we have a pair() combinator for parsing 
+// This is now quite easy since we have a pair() combinator for parsing
 // a tuple of values which we can combine with an identifier parser.
-fn attribute_pair<'a>() -> impl Parser<'a, (String, String)>
+fn attribute_pair<'a>() -> impl StrParser<'a, (String, String)>
 {
-    return pair(identifier, right(match_literal("="), quoted_string()));
+    return pair(
+        context("an identifier", identifier),
+        right(context("an '='", match_literal("=")), quoted_string()),
+    );
 }
 
-// combine the above with zero_or_more to build a vector of attributes 
-fn attributes<'a>() -> impl Parser<'a, Vec<(String, String)>>
+// combine the above with zero_or_more to build a vector of attributes
+fn attributes<'a>() -> impl StrParser<'a, Vec<(String, String)>>
 {
     return zero_or_more(right(one_or_more_space(), attribute_pair()));
 }
 
 
 // Starting element (or opening tag)
-fn element_start<'a>() -> impl Parser<'a, (String, Vec<(String, String)>)>
+fn element_start<'a>() -> impl StrParser<'a, (String, Vec<(String, String)>)>
 {
-    return right(match_literal("<"), pair(identifier, attributes()));
+    return right(
+        context("'<'", match_literal("<")),
+        pair(context("an identifier", identifier), attributes()),
+    );
 }
 
-// A complete element (with closing tag)
-// TODO : this results in an extremely complicated parse result
-//fn single_element<'a>() -> impl Parser<'a, Element>
-//{
-//    return map(
-//        left(element_start(), match_literal("/>")),
-//        | (name, attributes) | Element {
-//            name,
-//            attributes,
-//            children: vec![],
-//        }
-//    );
-//}
+// A complete element (self-closing tag)
+// Uses the method-based map() so the result type stays boxed instead
+// of exploding into a nested generic as more combinators are chained on.
+fn single_element<'a>() -> impl StrParser<'a, Element>
+{
+    return left(element_start(), context("'/>'", match_literal("/>")))
+        .map(|(name, attributes)| Element {
+            name,
+            attributes,
+            children: vec![],
+        });
+}
 
+/*
+ * and_then combinator
+ * Runs `parser`, then passes its output into `f` to produce a brand
+ * new parser chosen at runtime, which is then run on the remaining
+ * input. This is what lets a later parser depend on a value an
+ * earlier parser captured, e.g. checking a close tag against the
+ * name read from the matching open tag.
+ */
+fn and_then<I, E, P, F, A, B, NextP>(parser: P, f: F) -> impl Parser<I, B, E>
+where
+    P: Parser<I, A, E>,
+    NextP: Parser<I, B, E>,
+    F: Fn(A) -> NextP,
+{
+    move |input| match parser.parse(input)
+    {
+        Ok((next_input, result)) => f(result).parse(next_input),
+        Err(err) => Err(err),
+    }
+}
+
+// Closing tag for `expected_name`, e.g. "</parent>". Rejects the parse
+// if the identifier between "</" and ">" doesn't match.
+fn close_element<'a>(expected_name: String) -> impl StrParser<'a, String>
+{
+    return context(
+        "a matching close tag",
+        right(match_literal("</"), left(identifier, match_literal(">")))
+            .pred(move |name| name == &expected_name),
+    );
+}
 
-// ================ TESTS ================ //
+// An element with children, closed by a matching "</name>" tag, e.g.
+// "<parent><child/></parent>".
+fn parent_element<'a>() -> impl StrParser<'a, Element>
+{
+    return and_then(element_start(), |(name, attributes)|
+    {
+        right(
+            context("'>'", match_literal(">")),
+            left(zero_or_more(element()), close_element(name.clone())),
+        )
+        .map(move |children| Element {
+            name: name.clone(),
+            attributes: attributes.clone(),
+            children,
+        })
+    });
+}
+
+/*
+ * either combinator
+ * Try `parser1`; if it fails, rewind to the original input and try
+ * `parser2` instead. This is how a parser expresses alternation
+ * between two grammar rules.
+ */
+struct Choice<P1, P2> {
+    parser1: P1,
+    parser2: P2,
+}
+
+impl<I, E, P1, P2, A> Parser<I, A, E> for Choice<P1, P2>
+where
+    I: Copy,
+    E: MergeError,
+    P1: Parser<I, A, E>,
+    P2: Parser<I, A, E>,
+{
+    fn parse(&self, input: I) -> ParseResult<I, A, E>
+    {
+        match self.parser1.parse(input)
+        {
+            ok @ Ok(_) => ok,
+            Err(err1) => match self.parser2.parse(input)
+            {
+                ok @ Ok(_) => ok,
+                Err(err2) => Err(err1.merge(err2)),
+            },
+        }
+    }
+
+    fn grammar(&self) -> Grammar
+    {
+        Grammar::Choice(vec![self.parser1.grammar(), self.parser2.grammar()])
+    }
+}
+
+fn either<I, E, P1, P2, A>(parser1: P1, parser2: P2) -> Choice<P1, P2>
+where
+    I: Copy,
+    E: MergeError,
+    P1: Parser<I, A, E>,
+    P2: Parser<I, A, E>,
+{
+    Choice { parser1, parser2 }
+}
+
+/*
+ * whitespace_wrap
+ * Allow (and discard) leading/trailing whitespace around `parser`, so
+ * real-world XML with indentation and newlines between tags parses
+ * the same as the compact form.
+ */
+fn whitespace_wrap<'a, P, A>(parser: P) -> impl StrParser<'a, A>
+where
+    P: StrParser<'a, A>,
+{
+    return right(zero_or_more_space(), left(parser, zero_or_more_space()));
+}
+
+// Any element: a self-closing element or a parent element with children,
+// tolerant of surrounding whitespace. Boxed because it is mutually
+// recursive with parent_element() via zero_or_more(element()), which
+// would otherwise need an infinitely sized type.
+fn element<'a>() -> BoxedParser<'a, &'a str, Element, ParseError<'a>>
+{
+    return BoxedParser::new(whitespace_wrap(either(single_element(), parent_element())));
+}
+
+// Top-level entry point: parse a whole document, failing (rather than
+// silently dropping the tail) if anything is left over once `element()`
+// is done.
+fn parse_document<'a>(input: &'a str) -> Result<Element, ParseError<'a>>
+{
+    return element().parse_complete(input).map(|(_, el)| el);
+}
+
+
+// ================ TESTS ================
 
 #[test]
-fn test_identifier_parser() 
+fn test_identifier_parser()
 {
     assert_eq!(
         Ok(("", "i-am-an-identifier".to_string())),
@@ -304,7 +884,7 @@ fn test_identifier_parser()
 
 
 #[test]
-fn test_literal_parser() 
+fn test_literal_parser()
 {
     let parse_joe = match_literal("Hello Joe!");
 
@@ -314,13 +894,23 @@ fn test_literal_parser()
         Ok((" Hello Robert!", ())),         // consume "Hello Joe!", leaving "Hello Robert"
         parse_joe.parse("Hello Joe! Hello Robert!")
     );
-    
+
     assert_eq!(
         Err("Hello Mike!"),
         parse_joe.parse("Hello Mike!")
     );
 }
 
+// the generic combinator layer also runs over byte slices, not just &str
+#[test]
+fn test_literal_parser_over_bytes()
+{
+    let parse_magic = match_literal(&b"\x89PNG"[..]);
+
+    assert_eq!(Ok((&b""[..], ())), parse_magic.parse(&b"\x89PNG"[..]));
+    assert_eq!(Err(&b"GIF89a"[..]), parse_magic.parse(&b"GIF89a"[..]));
+}
+
 #[test]
 fn test_pair_combinator()
 {
@@ -350,13 +940,13 @@ fn test_right_combinator()
     assert_eq!(Err("!oops"), tag_opener.parse("<!oops"));
 }
 
-// * and . combinators 
+// * and . combinators
 #[test]
 fn test_one_or_more_combinator()
 {
     let parser = one_or_more(match_literal("ha"));
 
-    assert_eq!(Ok(("", vec![(), (), ()])), parser.parse("hahaha"));     
+    assert_eq!(Ok(("", vec![(), (), ()])), parser.parse("hahaha"));
     assert_eq!(Err("ahah"), parser.parse("ahah"));
     assert_eq!(Err(""), parser.parse(""));
 }
@@ -372,14 +962,78 @@ fn test_zero_or_more_combinator()
 }
 
 #[test]
-fn test_predicate_combinator() 
+fn test_predicate_combinator()
 {
     let parser = pred(any_char, |c| *c == 'o');
     assert_eq!(Ok(("mg", 'o')), parser.parse("omg")); // get the 'o' from omg
     assert_eq!(Err("lol"), parser.parse("lol"));
 }
 
-// test quoted string parser 
+// test that combinators report a symbolic Grammar describing what they match
+#[test]
+fn test_grammar_representation()
+{
+    assert_eq!("\"<\"", match_literal("<").grammar().to_string());
+
+    assert_eq!(
+        "{ \"x\" }",
+        zero_or_more(match_literal("x")).grammar().to_string()
+    );
+
+    assert_eq!(
+        "\"x\", { \"x\" }",
+        one_or_more(match_literal("x")).grammar().to_string()
+    );
+
+    assert_eq!(
+        "\"a\" | \"b\"",
+        either(match_literal("a"), match_literal("b")).grammar().to_string()
+    );
+
+    assert_eq!(
+        "a digit = \"1\"",
+        context("a digit", match_literal("1")).grammar().to_string()
+    );
+}
+
+// test that context attaches a label to an otherwise bare error
+#[test]
+fn test_context_combinator()
+{
+    let parser = context("a literal 'x'", match_literal("x"));
+
+    assert_eq!(
+        Err(ParseError { input: "y", expected: vec!["a literal 'x'"] }),
+        parser.parse("y")
+    );
+}
+
+// test that the offset of a ParseError is computed relative to the
+// original, full input rather than whatever slice failed
+#[test]
+fn test_parse_error_offset()
+{
+    let doc = "  <4a";
+    let (rest, _) = zero_or_more_space().parse(doc).unwrap();
+    let err = element_start().parse(rest).unwrap_err();
+
+    assert_eq!(err.offset(doc), 3);
+}
+
+// test that either merges the expected-sets of both alternatives when
+// they fail at the same position
+#[test]
+fn test_either_merges_expected_labels()
+{
+    let parser = either(context("'a'", match_literal("a")), context("'b'", match_literal("b")));
+
+    assert_eq!(
+        Err(ParseError { input: "c", expected: vec!["'a'", "'b'"] }),
+        parser.parse("c")
+    );
+}
+
+// test quoted string parser
 #[test]
 fn test_quoted_string_parser()
 {
@@ -390,7 +1044,7 @@ fn test_quoted_string_parser()
 }
 
 // test we can parse a single attribute
-#[test] 
+#[test]
 fn attribute_parser()
 {
     assert_eq!(Ok(("",
@@ -404,19 +1058,99 @@ fn attribute_parser()
 }
 
 // test we can parse a single element
-//#[test]
-//fn single_element_parser()
-//{
-//    assert_eq!(
-//        Ok(("", Element{
-//            name: "div".to_string(),
-//            attributes: vec![("class".to_string(), "float".to_string())],
-//            children: vec![]
-//            }
-//        )),
-//        single_element().parse("<div class=\"float\"/>")
-//    );
-//}
+#[test]
+fn single_element_parser()
+{
+    assert_eq!(
+        Ok(("", Element{
+            name: "div".to_string(),
+            attributes: vec![("class".to_string(), "float".to_string())],
+            children: vec![]
+            }
+        )),
+        single_element().parse("<div class=\"float\"/>")
+    );
+}
+
+
+// test we can parse a parent element with a child
+#[test]
+fn parent_element_parser()
+{
+    let doc = r#"<parent><child attribute="value"/></parent>"#;
+
+    assert_eq!(
+        Ok(("", Element {
+            name: "parent".to_string(),
+            attributes: vec![],
+            children: vec![
+                Element {
+                    name: "child".to_string(),
+                    attributes: vec![("attribute".to_string(), "value".to_string())],
+                    children: vec![],
+                },
+            ],
+        })),
+        element().parse(doc)
+    );
+}
+
+
+// test either tries the first parser, then falls back to the second
+#[test]
+fn test_either_combinator()
+{
+    let parser = either(match_literal("hello"), match_literal("goodbye"));
+
+    assert_eq!(Ok(("", ())), parser.parse("hello"));
+    assert_eq!(Ok(("", ())), parser.parse("goodbye"));
+    assert_eq!(Err("hi"), parser.parse("hi"));
+}
+
+// test we can parse an element with whitespace around and between tags
+#[test]
+fn whitespace_wrapped_element_parser()
+{
+    let doc = r#"
+        <parent>
+            <child attribute="value"/>
+        </parent>"#;
+
+    assert_eq!(
+        Ok(("", Element {
+            name: "parent".to_string(),
+            attributes: vec![],
+            children: vec![
+                Element {
+                    name: "child".to_string(),
+                    attributes: vec![("attribute".to_string(), "value".to_string())],
+                    children: vec![],
+                },
+            ],
+        })),
+        element().parse(doc)
+    );
+}
+
+// test that parse_document rejects trailing, unconsumed input instead
+// of silently dropping it
+#[test]
+fn test_parse_document_rejects_trailing_input()
+{
+    assert_eq!(
+        Ok(Element {
+            name: "a".to_string(),
+            attributes: vec![],
+            children: vec![],
+        }),
+        parse_document("<a/>")
+    );
+
+    assert_eq!(
+        Err(ParseError::new("garbage", "end of input")),
+        parse_document("<a/> garbage")
+    );
+}
 
 
 // ======== MAIN ======== //